@@ -0,0 +1,122 @@
+use prelude::*;
+
+const PATTERN_BITS: usize = 128;
+
+/// Alternating `0xFF00…` square wave, 16 bits per period: at the default
+/// pitch of 64 (a 4000 Hz bit clock) that's a 250 Hz tone, so plain CHIP-8
+/// ROMs that never touch `F002` still get a buzzer out of `SetSound`
+/// without having to load a pattern first.
+const DEFAULT_PATTERN: [Byte; 16] = [
+    0xff, 0x00, 0xff, 0x00, 0xff, 0x00, 0xff, 0x00,
+    0xff, 0x00, 0xff, 0x00, 0xff, 0x00, 0xff, 0x00,
+];
+
+const AMPLITUDE: i16 = 3000;
+
+/// `2^(k/48)` for `k` in `0..48`, used to turn a pitch byte into the XO-CHIP
+/// pattern bit clock without pulling in a transcendental-math crate.
+const EXP2_FRAC_48: [f32; 48] = [
+    1.0, 1.01455, 1.0293, 1.04427, 1.05946, 1.07487, 1.09051, 1.10637,
+    1.12246, 1.13879, 1.15535, 1.17216, 1.18921, 1.2065, 1.22405, 1.24186,
+    1.25992, 1.27825, 1.29684, 1.3157, 1.33484, 1.35426, 1.37395, 1.39394,
+    // k = 24 is exactly half an octave, i.e. 2^(1/2), so use the exact
+    // constant rather than a truncated literal.
+    core::f32::consts::SQRT_2, 1.43478, 1.45565, 1.47683, 1.49831, 1.5201, 1.54221, 1.56464,
+    1.5874, 1.61049, 1.63392, 1.65768, 1.68179, 1.70626, 1.73107, 1.75625,
+    1.7818, 1.80771, 1.83401, 1.86068, 1.88775, 1.91521, 1.94306, 1.97133,
+];
+
+/// `4000 * 2^((pitch - 64) / 48)`, the rate at which the 128-bit pattern
+/// buffer's bit clock advances for a given `FX3A` pitch.
+fn bit_clock_hz(pitch: Byte) -> f32 {
+    let diff = pitch as i32 - 64;
+    let octaves = diff.div_euclid(48);
+    let frac = EXP2_FRAC_48[diff.rem_euclid(48) as usize];
+
+    let base = 4000.0 * frac;
+    if octaves >= 0 {
+        base * (1u32 << octaves.min(30)) as f32
+    } else {
+        base / (1u32 << (-octaves).min(30)) as f32
+    }
+}
+
+/// Owns the XO-CHIP audio pattern buffer and turns it into PCM samples.
+///
+/// The phase accumulator persists across calls to `fill_samples`: if it
+/// were reset at the start of every buffer, the waveform would jump back
+/// to phase zero on each call and produce an audible click at every buffer
+/// boundary, so a frontend can call `fill_samples` from its audio callback
+/// with buffers of any size and get a continuous tone.
+pub struct Audio {
+    pattern: [Byte; 16],
+    pitch: Byte,
+    phase: f32,
+}
+
+impl Audio {
+    pub const fn new() -> Self {
+        Audio {
+            pattern: DEFAULT_PATTERN,
+            pitch: 64,
+            phase: 0.0,
+        }
+    }
+
+    pub(crate) fn load_pattern(&mut self, pattern: [Byte; 16]) {
+        self.pattern = pattern;
+    }
+
+    pub(crate) fn set_pitch(&mut self, pitch: Byte) {
+        self.pitch = pitch;
+    }
+
+    /// The raw 128-bit pattern buffer, for snapshotting.
+    pub(crate) fn pattern(&self) -> [Byte; 16] {
+        self.pattern
+    }
+
+    /// The current `FX3A` pitch, for snapshotting.
+    pub(crate) fn pitch(&self) -> Byte {
+        self.pitch
+    }
+
+    /// The current position in the pattern buffer, for snapshotting.
+    pub(crate) fn phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// Restore a pattern buffer, pitch and phase previously read via
+    /// `pattern`/`pitch`/`phase`, so a restored snapshot resumes playback
+    /// exactly where it left off instead of clicking back to phase zero.
+    pub(crate) fn load_state(&mut self, pattern: [Byte; 16], pitch: Byte, phase: f32) {
+        self.pattern = pattern;
+        self.pitch = pitch;
+        self.phase = phase;
+    }
+
+    fn pattern_bit(&self, i: usize) -> bool {
+        let byte = self.pattern[i / 8];
+        byte & (0x80 >> (i % 8)) != 0
+    }
+
+    /// Fill `out` with signed 16-bit PCM samples at `sample_rate`, emitting
+    /// silence (but still advancing the phase) whenever `playing` is false.
+    pub fn fill_samples(&mut self, out: &mut [i16], sample_rate: u32, playing: bool) {
+        let step = bit_clock_hz(self.pitch) / sample_rate as f32;
+
+        for sample in out.iter_mut() {
+            let bit = self.pattern_bit(self.phase as usize % PATTERN_BITS);
+            *sample = match (playing, bit) {
+                (false, _) => 0,
+                (true, true) => AMPLITUDE,
+                (true, false) => -AMPLITUDE,
+            };
+
+            self.phase += step;
+            if self.phase >= PATTERN_BITS as f32 {
+                self.phase -= PATTERN_BITS as f32;
+            }
+        }
+    }
+}
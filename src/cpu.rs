@@ -2,25 +2,8 @@ pub use prelude::*;
 pub use opcodes::*;
 pub use peripherals::Peripherals;
 
-use core::marker::PhantomData;
-
-pub trait Quirks {
-    const SHIFT_VY: bool;
-    const RESET_VF: bool;
-    const INCREMENT_PTR: bool;
-    const VIDEO_WAIT: bool;
-    const CLIP_SPRITES: bool;
-}
-
-pub struct DefaultQuirks;
-
-impl Quirks for DefaultQuirks {
-    const SHIFT_VY: bool = true;
-    const RESET_VF: bool = true;
-    const INCREMENT_PTR: bool = true;
-    const VIDEO_WAIT: bool = true;
-    const CLIP_SPRITES: bool = true;
-}
+use audio::Audio;
+use quirks::Quirks;
 
 enum State {
     Running,
@@ -29,8 +12,29 @@ enum State {
     WaitFrame,
 }
 
-pub struct CPU<Q: Quirks> {
-    quirks: PhantomData<Q>,
+const STATE_TAG_RUNNING: Byte = 0;
+const STATE_TAG_WAIT_PRESS: Byte = 1;
+const STATE_TAG_WAIT_RELEASE: Byte = 2;
+const STATE_TAG_WAIT_FRAME: Byte = 3;
+
+const FLAG_HIRES: Byte = 1 << 0;
+const FLAG_SHIFT_VY: Byte = 1 << 1;
+const FLAG_RESET_VF: Byte = 1 << 2;
+const FLAG_INCREMENT_PTR: Byte = 1 << 3;
+const FLAG_VIDEO_WAIT: Byte = 1 << 4;
+const FLAG_CLIP_SPRITES: Byte = 1 << 5;
+
+/// Size in bytes of the buffer `CPU::save_state`/`load_state` read and
+/// write: a version byte, `regs`/`ptr`/`pc`/`stack`/`sp`/`rnd`/`timer`/
+/// `sound_timer`, a tag+payload encoding of `State`, a flags byte covering
+/// `hires` and the runtime-switchable `quirks`, and the `Audio` pattern
+/// buffer/pitch/phase.
+pub const STATE_SIZE: usize = 1 + 16 + 2 + 2 + 16 * 2 + 1 + 2 + 1 + 1 + 1 + 1 + 2 + 1 + 16 + 1 + 4;
+
+const STATE_VERSION: Byte = 3;
+
+pub struct CPU {
+    pub quirks: Quirks,
     regs: [Byte; 16],
     ptr: Addr,
     pc: Addr,
@@ -38,13 +42,33 @@ pub struct CPU<Q: Quirks> {
     sp: usize,
     rnd: Addr,
     timer: Byte,
+    sound_timer: Byte,
     state: State,
+    audio: Audio,
+    hires: bool,
 }
 
-impl<Q: Quirks> CPU<Q> {
+impl CPU {
     pub const fn new() -> Self {
         CPU{
-            quirks: PhantomData,
+            quirks: Quirks::chip8(),
+            regs : [0; 16],
+            ptr: 0,
+            pc: 0x200,
+            stack: [0; 16],
+            sp: 0,
+            rnd: 0xf00f,
+            timer: 0,
+            sound_timer: 0,
+            state: State::Running,
+            audio: Audio::new(),
+            hires: false,
+        }
+    }
+
+    pub const fn with_quirks(quirks: Quirks) -> Self {
+        CPU{
+            quirks,
             regs : [0; 16],
             ptr: 0,
             pc: 0x200,
@@ -52,12 +76,16 @@ impl<Q: Quirks> CPU<Q> {
             sp: 0,
             rnd: 0xf00f,
             timer: 0,
+            sound_timer: 0,
             state: State::Running,
+            audio: Audio::new(),
+            hires: false,
         }
     }
 
     pub fn tick_frame(&mut self) {
         if self.timer > 0 { self.timer -= 1 };
+        if self.sound_timer > 0 { self.sound_timer -= 1 };
         self.next_random();
 
         if let State::WaitFrame = self.state {
@@ -65,6 +93,104 @@ impl<Q: Quirks> CPU<Q> {
         }
     }
 
+    /// Fill `out` with PCM samples for the current XO-CHIP pattern buffer,
+    /// to be called from the frontend's audio callback.
+    pub fn fill_samples(&mut self, out: &mut [i16], sample_rate: u32) {
+        self.audio.fill_samples(out, sample_rate, self.sound_timer > 0);
+    }
+
+    /// Write a versioned, little-endian snapshot of the CPU's own state
+    /// (not RAM or the framebuffer, which live behind `Peripherals`) into
+    /// `out`, returning the number of bytes written. `out` must be at
+    /// least `STATE_SIZE` bytes long.
+    pub fn save_state(&self, out: &mut [Byte]) -> usize {
+        let out = &mut out[..STATE_SIZE];
+
+        out[0] = STATE_VERSION;
+        out[1..17].copy_from_slice(&self.regs);
+        out[17..19].copy_from_slice(&self.ptr.to_le_bytes());
+        out[19..21].copy_from_slice(&self.pc.to_le_bytes());
+        for (i, addr) in self.stack.iter().enumerate() {
+            let o = 21 + i * 2;
+            out[o..o + 2].copy_from_slice(&addr.to_le_bytes());
+        }
+        out[53] = self.sp as Byte;
+        out[54..56].copy_from_slice(&self.rnd.to_le_bytes());
+        out[56] = self.timer;
+        out[57] = self.sound_timer;
+
+        let (tag, vx, payload) = match self.state {
+            State::Running => (STATE_TAG_RUNNING, 0, 0),
+            State::WaitPress(vx, prev) => (STATE_TAG_WAIT_PRESS, vx, prev),
+            State::WaitRelease(key) => (STATE_TAG_WAIT_RELEASE, key, 0),
+            State::WaitFrame => (STATE_TAG_WAIT_FRAME, 0, 0),
+        };
+        out[58] = tag;
+        out[59] = vx;
+        out[60..62].copy_from_slice(&payload.to_le_bytes());
+
+        let mut flags = 0;
+        if self.hires { flags |= FLAG_HIRES; }
+        if self.quirks.shift_vy { flags |= FLAG_SHIFT_VY; }
+        if self.quirks.reset_vf { flags |= FLAG_RESET_VF; }
+        if self.quirks.increment_ptr { flags |= FLAG_INCREMENT_PTR; }
+        if self.quirks.video_wait { flags |= FLAG_VIDEO_WAIT; }
+        if self.quirks.clip_sprites { flags |= FLAG_CLIP_SPRITES; }
+        out[62] = flags;
+
+        out[63..79].copy_from_slice(&self.audio.pattern());
+        out[79] = self.audio.pitch();
+        out[80..84].copy_from_slice(&self.audio.phase().to_le_bytes());
+
+        STATE_SIZE
+    }
+
+    /// Restore CPU state previously written by `save_state`. Fails without
+    /// mutating `self` if `data` is too short or carries an unknown
+    /// version tag.
+    pub fn load_state(&mut self, data: &[Byte]) -> Result<(), ()> {
+        if data.len() < STATE_SIZE || data[0] != STATE_VERSION {
+            return Err(());
+        }
+
+        self.regs.copy_from_slice(&data[1..17]);
+        self.ptr = Addr::from_le_bytes([data[17], data[18]]);
+        self.pc = Addr::from_le_bytes([data[19], data[20]]);
+        for (i, addr) in self.stack.iter_mut().enumerate() {
+            let o = 21 + i * 2;
+            *addr = Addr::from_le_bytes([data[o], data[o + 1]]);
+        }
+        self.sp = (data[53] as usize) & 0x0f;
+        self.rnd = Addr::from_le_bytes([data[54], data[55]]);
+        self.timer = data[56];
+        self.sound_timer = data[57];
+
+        let vx = data[59];
+        let payload = u16::from_le_bytes([data[60], data[61]]);
+        self.state = match data[58] {
+            STATE_TAG_WAIT_PRESS => State::WaitPress(vx, payload),
+            STATE_TAG_WAIT_RELEASE => State::WaitRelease(vx),
+            STATE_TAG_WAIT_FRAME => State::WaitFrame,
+            _ => State::Running,
+        };
+
+        let flags = data[62];
+        self.hires = flags & FLAG_HIRES != 0;
+        self.quirks.shift_vy = flags & FLAG_SHIFT_VY != 0;
+        self.quirks.reset_vf = flags & FLAG_RESET_VF != 0;
+        self.quirks.increment_ptr = flags & FLAG_INCREMENT_PTR != 0;
+        self.quirks.video_wait = flags & FLAG_VIDEO_WAIT != 0;
+        self.quirks.clip_sprites = flags & FLAG_CLIP_SPRITES != 0;
+
+        let mut pattern = [0; 16];
+        pattern.copy_from_slice(&data[63..79]);
+        let pitch = data[79];
+        let phase = f32::from_le_bytes([data[80], data[81], data[82], data[83]]);
+        self.audio.load_state(pattern, pitch, phase);
+
+        Ok(())
+    }
+
     fn eval(&self, arg: Arg) -> Byte {
         match arg {
             Arg::Reg(vx) => self.regs[vx as usize],
@@ -72,12 +198,12 @@ impl<Q: Quirks> CPU<Q> {
         }
     }
 
-    fn arith(op: Arith, x: Byte, y: Byte) -> (Byte, Option<bool>) {
+    fn arith(&self, op: Arith, x: Byte, y: Byte) -> (Byte, Option<bool>) {
         match op {
             Arith::Load => (y, None),
-            Arith::Or => (x | y, if Q::RESET_VF { Some(false) } else { None }),
-            Arith::And => (x & y, if Q::RESET_VF { Some(false) } else { None }),
-            Arith::XOr => (x ^ y, if Q::RESET_VF { Some(false) } else { None }),
+            Arith::Or => (x | y, if self.quirks.reset_vf { Some(false) } else { None }),
+            Arith::And => (x & y, if self.quirks.reset_vf { Some(false) } else { None }),
+            Arith::XOr => (x ^ y, if self.quirks.reset_vf { Some(false) } else { None }),
             Arith::Add => {
                 let (z, f) = u8::overflowing_add(x, y);
                 (z, Some(f))
@@ -91,11 +217,11 @@ impl<Q: Quirks> CPU<Q> {
                 (z, Some(!f))
             },
             Arith::ShiftL => {
-                let arg = if Q::SHIFT_VY { y } else { x };
+                let arg = if self.quirks.shift_vy { y } else { x };
                 (arg << 1, Some(arg & 0x80 != 0))
             },
             Arith::ShiftR => {
-                let arg = if Q::SHIFT_VY { y } else { x };
+                let arg = if self.quirks.shift_vy { y } else { x };
                 (arg >> 1, Some(arg & 0x01 != 0))
             }
         }
@@ -113,6 +239,37 @@ impl<Q: Quirks> CPU<Q> {
         self.rnd as Byte
     }
 
+    fn resolution(&self) -> (Byte, Byte) {
+        if self.hires { (SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1) } else { (SCREEN_WIDTH_LO - 1, SCREEN_HEIGHT_LO - 1) }
+    }
+
+    /// Mask covering just the `width`-bit active field at the top of a
+    /// `ScreenRow` (column 0 at the MSB). In lo-res mode `width` is 64, so
+    /// the register's low 64 "dead zone" bits never count as on-screen
+    /// pixels.
+    fn active_mask(width: u32) -> ScreenRow {
+        if width == SCREEN_WIDTH as u32 {
+            ScreenRow::MAX
+        } else {
+            ((1u128 << width) - 1) << (SCREEN_WIDTH as u32 - width)
+        }
+    }
+
+    /// Rotate the `width`-bit field occupying the top of `row` (column 0 at
+    /// the MSB of the full `ScreenRow`) right by `amount`. Any lower,
+    /// currently-inactive bits of `row` are cleared in the result.
+    fn rotate_row(row: ScreenRow, amount: u32, width: u32) -> ScreenRow {
+        if width == SCREEN_WIDTH as u32 {
+            return row.rotate_right(amount);
+        }
+
+        let shift = SCREEN_WIDTH as u32 - width;
+        let mask = (1u128 << width) - 1;
+        let active = (row >> shift) & mask;
+        let rotated = (active >> amount) | (active << (width - amount));
+        (rotated & mask) << shift
+    }
+
     pub fn step<P>(&mut self, io: &mut P) where P: Peripherals {
         match self.state {
             State::Running => self.exec(io),
@@ -183,7 +340,7 @@ impl<Q: Quirks> CPU<Q> {
             Op::Arith(op, vx, vy) => {
                 let x = self.regs[vx as usize];
                 let y = self.regs[vy as usize];
-                let (z, flag) = Self::arith(op, x, y);
+                let (z, flag) = self.arith(op, x, y);
                 self.regs[vx as usize] = z;
                 flag.map(|flag| { self.set_flag(flag); });
             },
@@ -215,7 +372,7 @@ impl<Q: Quirks> CPU<Q> {
                 io.write_ram(self.ptr, x / 100);
                 io.write_ram(self.ptr + 1, (x % 100) / 10);
                 io.write_ram(self.ptr + 2, x % 10);
-                if Q::INCREMENT_PTR {
+                if self.quirks.increment_ptr {
                     self.ptr += 3;
                 }
             },
@@ -223,7 +380,7 @@ impl<Q: Quirks> CPU<Q> {
                 for i in 0..vx as usize +1 {
                     io.write_ram(self.ptr + i as Addr, self.regs[i])
                 }
-                if Q::INCREMENT_PTR {
+                if self.quirks.increment_ptr {
                     self.ptr += 3;
                 }
             },
@@ -235,17 +392,32 @@ impl<Q: Quirks> CPU<Q> {
             Op::Draw(vx, vy, n) => {
                 let mut collision = false;
 
-                let yd0 = self.regs[vy as usize] & 0x1f;
-                let xd = self.regs[vx as usize] & 0x3f;
+                let (max_x, max_y) = self.resolution();
+                let width = max_x as u32 + 1;
 
-                for i in 0..n {
+                // `DXY0` is SUPER-CHIP's 16x16 sprite form: 16 rows of two
+                // bytes each, instead of N rows of one byte.
+                let (rows, sprite_bytes) = if n == 0 { (16, 2) } else { (n, 1) };
+
+                let yd0 = self.regs[vy as usize] & max_y;
+                let xd = self.regs[vx as usize] & max_x;
+
+                for i in 0..rows {
                     let yd = yd0 + i;
-                    if Q::CLIP_SPRITES && yd > 31 { break }
+                    if self.quirks.clip_sprites && yd > max_y { break }
+
+                    let yd = yd & max_y;
 
-                    let yd = yd & 0x1f;
-                    let dat = io.read_ram(self.ptr + i as Addr);
-                    let row0 = (dat as ScreenRow) << 56;
-                    let row = if Q::CLIP_SPRITES { row0 >> xd } else { row0.rotate_right(xd as u32) };
+                    let mut row0: ScreenRow = 0;
+                    for b in 0..sprite_bytes {
+                        let dat = io.read_ram(self.ptr + (i as Addr * sprite_bytes as Addr) + b as Addr);
+                        row0 |= (dat as ScreenRow) << (SCREEN_WIDTH as u32 - 8 - b as u32 * 8);
+                    }
+                    let row = if self.quirks.clip_sprites {
+                        (row0 >> xd) & Self::active_mask(width)
+                    } else {
+                        Self::rotate_row(row0, xd as u32, width)
+                    };
 
                     let old_row = io.get_pixel_row(yd);
                     let new_row = old_row ^ row;
@@ -253,13 +425,40 @@ impl<Q: Quirks> CPU<Q> {
                     io.set_pixel_row(yd, new_row);
                 };
                 self.set_flag(collision);
-                if Q::VIDEO_WAIT { self.state = State::WaitFrame };
+                if self.quirks.video_wait { self.state = State::WaitFrame };
             },
             Op::ClearScr => {
-                for y in 0..32 {
+                let (_, max_y) = self.resolution();
+                for y in 0..=max_y {
                     io.set_pixel_row(y, 0);
                 }
-                if Q::VIDEO_WAIT { self.state = State::WaitFrame };
+                if self.quirks.video_wait { self.state = State::WaitFrame };
+            },
+            Op::HiRes(on) => {
+                self.hires = on;
+            },
+            Op::ScrollDown(n) => {
+                let (_, max_y) = self.resolution();
+                for y in (0..=max_y).rev() {
+                    let row = if y >= n { io.get_pixel_row(y - n) } else { 0 };
+                    io.set_pixel_row(y, row);
+                }
+            },
+            Op::ScrollRight => {
+                let (max_x, max_y) = self.resolution();
+                let mask = Self::active_mask(max_x as u32 + 1);
+                for y in 0..=max_y {
+                    let row = io.get_pixel_row(y);
+                    io.set_pixel_row(y, (row >> 4) & mask);
+                }
+            },
+            Op::ScrollLeft => {
+                let (max_x, max_y) = self.resolution();
+                let mask = Self::active_mask(max_x as u32 + 1);
+                for y in 0..=max_y {
+                    let row = io.get_pixel_row(y);
+                    io.set_pixel_row(y, (row << 4) & mask);
+                }
             },
             Op::SkipKey(cond, vx) => {
                 let pressed = io.get_keys() & (1 << self.regs[vx as usize]) != 0;
@@ -275,8 +474,85 @@ impl<Q: Quirks> CPU<Q> {
                 self.state = State::WaitPress(vx, 0xffff);
             },
             Op::SetSound(vx) => {
-                io.set_sound(self.regs[vx as usize]);
+                let val = self.regs[vx as usize];
+                self.sound_timer = val;
+                io.set_sound(val);
+            },
+            Op::LoadPattern => {
+                let mut pattern = [0; 16];
+                for i in 0..16 {
+                    pattern[i] = io.read_ram(self.ptr + i as Addr);
+                }
+                self.audio.load_pattern(pattern);
+            },
+            Op::SetPitch(vx) => {
+                self.audio.set_pitch(self.regs[vx as usize]);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_load_roundtrips_full_state() {
+        let mut cpu = CPU::with_quirks(Quirks::schip());
+        cpu.regs = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+        cpu.ptr = 0x300;
+        cpu.pc = 0x204;
+        cpu.stack = [0x210; 16];
+        cpu.sp = 5;
+        cpu.rnd = 0xbeef;
+        cpu.timer = 7;
+        cpu.sound_timer = 9;
+        cpu.state = State::WaitPress(3, 0x55);
+        cpu.hires = true;
+        cpu.audio.load_pattern([0xaa; 16]);
+        cpu.audio.set_pitch(100);
+
+        let mut buf = [0; STATE_SIZE];
+        let written = cpu.save_state(&mut buf);
+        assert_eq!(written, STATE_SIZE);
+
+        let mut restored = CPU::new();
+        restored.load_state(&buf).unwrap();
+
+        assert_eq!(restored.regs, cpu.regs);
+        assert_eq!(restored.ptr, cpu.ptr);
+        assert_eq!(restored.pc, cpu.pc);
+        assert_eq!(restored.stack, cpu.stack);
+        assert_eq!(restored.sp, cpu.sp);
+        assert_eq!(restored.rnd, cpu.rnd);
+        assert_eq!(restored.timer, cpu.timer);
+        assert_eq!(restored.sound_timer, cpu.sound_timer);
+        assert_eq!(restored.hires, cpu.hires);
+        assert_eq!(restored.quirks.shift_vy, cpu.quirks.shift_vy);
+        assert_eq!(restored.quirks.reset_vf, cpu.quirks.reset_vf);
+        assert_eq!(restored.quirks.increment_ptr, cpu.quirks.increment_ptr);
+        assert_eq!(restored.quirks.video_wait, cpu.quirks.video_wait);
+        assert_eq!(restored.quirks.clip_sprites, cpu.quirks.clip_sprites);
+        assert_eq!(restored.audio.pattern(), cpu.audio.pattern());
+        assert_eq!(restored.audio.pitch(), cpu.audio.pitch());
+
+        match restored.state {
+            State::WaitPress(vx, prev) => {
+                assert_eq!(vx, 3);
+                assert_eq!(prev, 0x55);
             },
+            _ => panic!("expected State::WaitPress to round-trip"),
         }
     }
+
+    #[test]
+    fn load_state_rejects_short_or_wrong_version_buffer() {
+        let mut cpu = CPU::new();
+
+        assert!(cpu.load_state(&[STATE_VERSION]).is_err());
+
+        let mut buf = [0; STATE_SIZE];
+        buf[0] = STATE_VERSION.wrapping_add(1);
+        assert!(cpu.load_state(&buf).is_err());
+    }
 }
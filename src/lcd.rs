@@ -8,18 +8,28 @@ const COLOR_ON_GRID  : u32 = 0xff_20_38_20;
 const COLOR_OFF      : u32 = 0xff_73_bd_71;
 const COLOR_OFF_GRID : u32 = 0xff_63_ad_61;
 
-pub fn draw_lcd(framebuf: &FrameBuf, pixbuf: &mut [u32], padding: (usize, usize), scaling: (usize, usize)) {
+/// Draws `framebuf` into `pixbuf`. `hires` selects whether the buffer
+/// holds a 128x64 SUPER-CHIP frame or a 64x32 lo-res one (addressed in the
+/// top-left corner of the same `ScreenRow`s); lo-res is rendered at twice
+/// `scaling` so both modes fill the same physical display area.
+pub fn draw_lcd(framebuf: &FrameBuf, pixbuf: &mut [u32], padding: (usize, usize), scaling: (usize, usize), hires: bool) {
     let (pad_x, pad_y) = padding;
-    let (scale_x, scale_y) = scaling;
+    let (scale_x, scale_y) = if hires { scaling } else { (scaling.0 * 2, scaling.1 * 2) };
 
-    let rowstride = scale_x as usize * (SCREEN_WIDTH as usize + 2 * pad_x);
+    let (width, height) = if hires {
+        (SCREEN_WIDTH as usize, SCREEN_HEIGHT as usize)
+    } else {
+        (SCREEN_WIDTH_LO as usize, SCREEN_HEIGHT_LO as usize)
+    };
 
-    for (y, yp) in pad(pad_y as usize, 0..SCREEN_HEIGHT as usize).enumerate() {
+    let rowstride = scale_x as usize * (width + 2 * pad_x);
+
+    for (y, yp) in pad(pad_y as usize, 0..height).enumerate() {
         let mut row = if let Some(row_idx) = yp { framebuf[row_idx] } else { 0 };
 
-        for (x, xp) in pad(pad_x, 0..SCREEN_WIDTH as usize).enumerate() {
+        for (x, xp) in pad(pad_x, 0..width).enumerate() {
             let pixel = if let Some(_) = xp {
-                let pixel = row & (1 << 63) != 0;
+                let pixel = row & (1 << (SCREEN_WIDTH as u32 - 1)) != 0;
                 row <<= 1;
                 pixel
             } else {
@@ -5,6 +5,9 @@ pub mod opcodes;
 pub mod peripherals;
 pub mod quirks;
 pub mod cpu;
+pub mod audio;
+pub mod snapshot;
+pub mod record;
 pub mod font;
 mod padded;
 pub mod lcd;
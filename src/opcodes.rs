@@ -0,0 +1,119 @@
+use prelude::*;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Cmp {
+    Eq,
+    NEq,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Arg {
+    Reg(Nybble),
+    Imm(Byte),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Arith {
+    Load,
+    Or,
+    And,
+    XOr,
+    Add,
+    Sub,
+    SubFlip,
+    ShiftL,
+    ShiftR,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Sys(Addr),
+    Call(Addr),
+    Ret,
+    Jump(Addr),
+    Skip(Cmp, Nybble, Arg),
+    LoadImm(Nybble, Byte),
+    AddImm(Nybble, Byte),
+    Arith(Arith, Nybble, Nybble),
+    LoadI(Addr),
+    AddI(Nybble),
+    GetTimer(Nybble),
+    SetTimer(Nybble),
+    JumpV0(Addr),
+    Random(Nybble, Byte),
+    Hex(Nybble),
+    StoreBCD(Nybble),
+    Save(Nybble),
+    Restore(Nybble),
+    Draw(Nybble, Nybble, Nybble),
+    ClearScr,
+    SkipKey(Cmp, Nybble),
+    WaitKey(Nybble),
+    SetSound(Nybble),
+    /// `F002`: load the 16-byte XO-CHIP audio pattern buffer from RAM starting at `I`.
+    LoadPattern,
+    /// `FX3A`: set the XO-CHIP playback pitch from `VX`.
+    SetPitch(Nybble),
+    /// `00FE`/`00FF`: switch to lo-res (`false`) or SUPER-CHIP hi-res (`true`).
+    HiRes(bool),
+    /// `00CN`: scroll the screen down by `N` pixel rows.
+    ScrollDown(Nybble),
+    /// `00FB`: scroll the screen right by 4 pixels.
+    ScrollRight,
+    /// `00FC`: scroll the screen left by 4 pixels.
+    ScrollLeft,
+}
+
+pub fn decode(hi: Byte, lo: Byte) -> Option<Op> {
+    let n1 = hi >> 4;
+    let n2 = hi & 0x0f;
+    let n3 = lo >> 4;
+    let n4 = lo & 0x0f;
+    let addr = (n2 as Addr) << 8 | lo as Addr;
+
+    Some(match (n1, n2, n3, n4) {
+        (0x0, 0x0, 0xc, n) => Op::ScrollDown(n),
+        (0x0, 0x0, 0xe, 0x0) => Op::ClearScr,
+        (0x0, 0x0, 0xe, 0xe) => Op::Ret,
+        (0x0, 0x0, 0xf, 0xb) => Op::ScrollRight,
+        (0x0, 0x0, 0xf, 0xc) => Op::ScrollLeft,
+        (0x0, 0x0, 0xf, 0xe) => Op::HiRes(false),
+        (0x0, 0x0, 0xf, 0xf) => Op::HiRes(true),
+        (0x0, _, _, _) => Op::Sys(addr),
+        (0x1, _, _, _) => Op::Jump(addr),
+        (0x2, _, _, _) => Op::Call(addr),
+        (0x3, x, _, _) => Op::Skip(Cmp::Eq, x, Arg::Imm(lo)),
+        (0x4, x, _, _) => Op::Skip(Cmp::NEq, x, Arg::Imm(lo)),
+        (0x5, x, y, 0x0) => Op::Skip(Cmp::Eq, x, Arg::Reg(y)),
+        (0x6, x, _, _) => Op::LoadImm(x, lo),
+        (0x7, x, _, _) => Op::AddImm(x, lo),
+        (0x8, x, y, 0x0) => Op::Arith(Arith::Load, x, y),
+        (0x8, x, y, 0x1) => Op::Arith(Arith::Or, x, y),
+        (0x8, x, y, 0x2) => Op::Arith(Arith::And, x, y),
+        (0x8, x, y, 0x3) => Op::Arith(Arith::XOr, x, y),
+        (0x8, x, y, 0x4) => Op::Arith(Arith::Add, x, y),
+        (0x8, x, y, 0x5) => Op::Arith(Arith::Sub, x, y),
+        (0x8, x, y, 0x6) => Op::Arith(Arith::ShiftR, x, y),
+        (0x8, x, y, 0x7) => Op::Arith(Arith::SubFlip, x, y),
+        (0x8, x, y, 0xe) => Op::Arith(Arith::ShiftL, x, y),
+        (0x9, x, y, 0x0) => Op::Skip(Cmp::NEq, x, Arg::Reg(y)),
+        (0xa, _, _, _) => Op::LoadI(addr),
+        (0xb, _, _, _) => Op::JumpV0(addr),
+        (0xc, x, _, _) => Op::Random(x, lo),
+        (0xd, x, y, n) => Op::Draw(x, y, n),
+        (0xe, x, 0x9, 0xe) => Op::SkipKey(Cmp::Eq, x),
+        (0xe, x, 0xa, 0x1) => Op::SkipKey(Cmp::NEq, x),
+        (0xf, 0x0, 0x0, 0x2) => Op::LoadPattern,
+        (0xf, x, 0x0, 0x7) => Op::GetTimer(x),
+        (0xf, x, 0x0, 0xa) => Op::WaitKey(x),
+        (0xf, x, 0x1, 0x5) => Op::SetTimer(x),
+        (0xf, x, 0x1, 0x8) => Op::SetSound(x),
+        (0xf, x, 0x1, 0xe) => Op::AddI(x),
+        (0xf, x, 0x2, 0x9) => Op::Hex(x),
+        (0xf, x, 0x3, 0x3) => Op::StoreBCD(x),
+        (0xf, x, 0x3, 0xa) => Op::SetPitch(x),
+        (0xf, x, 0x5, 0x5) => Op::Save(x),
+        (0xf, x, 0x6, 0x5) => Op::Restore(x),
+        _ => return None,
+    })
+}
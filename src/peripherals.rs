@@ -10,4 +10,20 @@ pub trait Peripherals {
 
     fn read_ram(&self, addr: Addr) -> Byte;
     fn write_ram(&mut self, addr: Addr, val: Byte);
+
+    /// Bulk RAM snapshot for save-states, built on top of `read_ram` so
+    /// implementors get it for free; override if the backing store allows
+    /// a faster bulk copy.
+    fn dump_ram(&self, out: &mut [Byte]) {
+        for (addr, slot) in out.iter_mut().enumerate() {
+            *slot = self.read_ram(addr as Addr);
+        }
+    }
+
+    /// Inverse of `dump_ram`, for restoring a save-state.
+    fn load_ram(&mut self, data: &[Byte]) {
+        for (addr, &val) in data.iter().enumerate() {
+            self.write_ram(addr as Addr, val);
+        }
+    }
 }
@@ -3,7 +3,19 @@ pub type Addr = u16;
 pub type Nybble = Byte;
 
 pub type ScreenY = Byte;
-pub type ScreenRow = u64;
 
-pub const SCREEN_WIDTH : u8 = 64;
-pub const SCREEN_HEIGHT : u8 = 32;
+/// Wide enough to hold a full SUPER-CHIP hi-res scanline (`SCREEN_WIDTH`
+/// bits); a plain CHIP-8/lo-res row only ever uses the top
+/// `SCREEN_WIDTH_LO` bits of it, column 0 at the MSB.
+pub type ScreenRow = u128;
+
+pub const SCREEN_WIDTH : u8 = 128;
+pub const SCREEN_HEIGHT : u8 = 64;
+
+/// Logical resolution of plain CHIP-8 ("lo-res") ROMs, toggled via `00FE`.
+/// The framebuffer is always `SCREEN_WIDTH` x `SCREEN_HEIGHT`; lo-res mode
+/// just addresses its top-left corner.
+pub const SCREEN_WIDTH_LO : u8 = 64;
+pub const SCREEN_HEIGHT_LO : u8 = 32;
+
+pub const RAM_SIZE : usize = 4096;
@@ -1,3 +1,5 @@
+use prelude::*;
+
 #[derive(Clone, Copy)]
 pub struct Quirks {
     pub shift_vy: bool,
@@ -9,6 +11,13 @@ pub struct Quirks {
 
 impl Default for Quirks {
     fn default() -> Self {
+        Quirks::chip8()
+    }
+}
+
+impl Quirks {
+    /// Original COSMAC VIP CHIP-8 behaviour.
+    pub const fn chip8() -> Self {
         Quirks {
             shift_vy: true,
             reset_vf: true,
@@ -17,4 +26,75 @@ impl Default for Quirks {
             clip_sprites: true,
         }
     }
+
+    /// SUPER-CHIP 1.1: shifts work on `VX` alone, BCD store and `save`
+    /// leave `I` unchanged, no per-frame display wait, sprites still clip
+    /// at the screen edge.
+    pub const fn schip() -> Self {
+        Quirks {
+            shift_vy: false,
+            reset_vf: false,
+            increment_ptr: false,
+            video_wait: false,
+            clip_sprites: true,
+        }
+    }
+
+    /// XO-CHIP: like SCHIP, but BCD store and `save` still advance `I` and
+    /// sprites wrap around the screen instead of clipping.
+    pub const fn xochip() -> Self {
+        Quirks {
+            shift_vy: false,
+            reset_vf: false,
+            increment_ptr: true,
+            video_wait: false,
+            clip_sprites: false,
+        }
+    }
+}
+
+/// FNV-1a, just to turn a ROM's bytes into a lookup key — not
+/// cryptographic, just stable and cheap.
+fn rom_hash(rom: &[Byte]) -> u64 {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET;
+    for &byte in rom {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash
+}
+
+/// A `rom_hash` → quirk-profile lookup table, as consulted by
+/// `profile_for_rom`.
+pub type RomProfileTable = &'static [(u64, fn() -> Quirks)];
+
+/// The engine's own built-in ROM table. Ships empty: seeding it with real
+/// entries needs hashes taken from verified ROM dumps, and this crate
+/// doesn't carry a ROM corpus to hash. Frontends that maintain one should
+/// build their own `RomProfileTable` from it and pass it to
+/// `profile_for_rom_in` instead of waiting for this one to grow.
+pub const ROM_PROFILES: RomProfileTable = &[];
+
+/// Auto-select a quirk profile for `rom`'s content, falling back to plain
+/// CHIP-8 for anything not in `ROM_PROFILES`.
+///
+/// `ROM_PROFILES` ships empty, so this is currently always `Quirks::chip8()`;
+/// callers with a verified ROM corpus should use `profile_for_rom_in` with
+/// their own table instead.
+pub fn profile_for_rom(rom: &[Byte]) -> Quirks {
+    profile_for_rom_in(rom, ROM_PROFILES)
+}
+
+/// Auto-select a quirk profile for `rom`'s content by looking its
+/// `rom_hash` up in `table`, falling back to plain CHIP-8 for anything not
+/// present.
+pub fn profile_for_rom_in(rom: &[Byte], table: RomProfileTable) -> Quirks {
+    let hash = rom_hash(rom);
+    table.iter()
+        .find(|&&(h, _)| h == hash)
+        .map(|&(_, profile)| profile())
+        .unwrap_or_else(Quirks::chip8)
 }
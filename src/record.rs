@@ -0,0 +1,211 @@
+//! Frame-diff recording codec for gameplay capture.
+//!
+//! Borrows the skip/literal threshold scheme from the MS Video 1 encoder,
+//! specialized for the 1-bpp `FrameBuf`: unchanged scanlines are run-length
+//! coded away, so static-screen CHIP-8 scenes are nearly free to store.
+//! Unlike a colour codec there's no vector quantization to do.
+
+use prelude::*;
+use lcd::FrameBuf;
+
+const KEYFRAME_MARKER: Byte = 0x02;
+const SKIP_TOKEN: Byte = 0x00;
+const LITERAL_TOKEN: Byte = 0x01;
+
+const MODE_LO: Byte = 0x00;
+const MODE_HI: Byte = 0x01;
+
+/// Encodes a stream of `FrameBuf`s into skip/literal tokens, forcing a
+/// keyframe (all rows as literals) every `keyframe_interval` frames so
+/// playback can seek.
+pub struct Recorder {
+    prev: FrameBuf,
+    keyframe_interval: u32,
+    frames_since_keyframe: u32,
+}
+
+impl Recorder {
+    pub fn new(keyframe_interval: u32) -> Self {
+        Recorder {
+            prev: [0; SCREEN_HEIGHT as usize],
+            keyframe_interval,
+            frames_since_keyframe: 0,
+        }
+    }
+
+    /// Encode `fb`, captured while in hi-res (SUPER-CHIP) or lo-res mode
+    /// per `hires`, writing tokens to `out`. `hires` is stored per frame
+    /// (not just on keyframes) so a recording that toggles `00FE`/`00FF`
+    /// mid-session still decodes to the right resolution for every frame.
+    pub fn push_frame(&mut self, fb: &FrameBuf, hires: bool, out: &mut impl FnMut(Byte)) {
+        out(if hires { MODE_HI } else { MODE_LO });
+
+        if self.frames_since_keyframe == 0 {
+            out(KEYFRAME_MARKER);
+            for row in fb.iter() {
+                out(LITERAL_TOKEN);
+                for b in row.to_be_bytes().iter() {
+                    out(*b);
+                }
+            }
+        } else {
+            let mut y = 0;
+            while y < fb.len() {
+                if fb[y] == self.prev[y] {
+                    let mut run = 1;
+                    while y + run < fb.len() && fb[y + run] == self.prev[y + run] {
+                        run += 1;
+                    }
+                    out(SKIP_TOKEN);
+                    out(run as Byte);
+                    y += run;
+                } else {
+                    out(LITERAL_TOKEN);
+                    for b in fb[y].to_be_bytes().iter() {
+                        out(*b);
+                    }
+                    y += 1;
+                }
+            }
+        }
+
+        self.prev = *fb;
+        self.frames_since_keyframe += 1;
+        if self.frames_since_keyframe >= self.keyframe_interval {
+            self.frames_since_keyframe = 0;
+        }
+    }
+}
+
+/// Decodes the stream produced by `Recorder` back into `FrameBuf`s, e.g.
+/// for playback through `lcd::draw_lcd`.
+pub struct Decoder {
+    prev: FrameBuf,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Decoder { prev: [0; SCREEN_HEIGHT as usize] }
+    }
+
+    fn read_row(input: &mut impl Iterator<Item = Byte>) -> Option<ScreenRow> {
+        let mut bytes = [0; 16];
+        for b in bytes.iter_mut() {
+            *b = input.next()?;
+        }
+        Some(ScreenRow::from_be_bytes(bytes))
+    }
+
+    /// Decode the next frame from `input`, or `None` if the stream ends
+    /// cleanly before a full frame is available. Returns the decoded
+    /// `FrameBuf` together with the hi-res flag it was captured with, so
+    /// the caller knows which `draw_lcd(..., hires)` to use for it.
+    pub fn next_frame(&mut self, input: &mut impl Iterator<Item = Byte>) -> Option<(FrameBuf, bool)> {
+        let hires = input.next()? == MODE_HI;
+        let first = input.next()?;
+
+        if first == KEYFRAME_MARKER {
+            for row in self.prev.iter_mut() {
+                let tag = input.next()?;
+                debug_assert_eq!(tag, LITERAL_TOKEN);
+                *row = Self::read_row(input)?;
+            }
+        } else {
+            let mut y = 0;
+            let mut tag = first;
+            loop {
+                match tag {
+                    SKIP_TOKEN => {
+                        y += input.next()? as usize;
+                    },
+                    LITERAL_TOKEN => {
+                        self.prev[y] = Self::read_row(input)?;
+                        y += 1;
+                    },
+                    _ => return None,
+                }
+                if y >= self.prev.len() {
+                    break;
+                }
+                tag = input.next()?;
+            }
+        }
+
+        Some((self.prev, hires))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fixed-capacity byte sink so these tests stay alloc-free, matching
+    /// the rest of this `no_std` crate.
+    struct FixedBuf {
+        bytes: [Byte; 8192],
+        len: usize,
+    }
+
+    impl FixedBuf {
+        fn new() -> Self {
+            FixedBuf { bytes: [0; 8192], len: 0 }
+        }
+
+        fn push(&mut self, b: Byte) {
+            self.bytes[self.len] = b;
+            self.len += 1;
+        }
+
+        fn iter(&self) -> impl Iterator<Item = Byte> + '_ {
+            self.bytes[..self.len].iter().copied()
+        }
+    }
+
+    fn encode_all(frames: &[(FrameBuf, bool)], keyframe_interval: u32) -> FixedBuf {
+        let mut recorder = Recorder::new(keyframe_interval);
+        let mut out = FixedBuf::new();
+        for (fb, hires) in frames {
+            recorder.push_frame(fb, *hires, &mut |b| out.push(b));
+        }
+        out
+    }
+
+    #[test]
+    fn roundtrips_static_scene() {
+        let mut fb: FrameBuf = [0; SCREEN_HEIGHT as usize];
+        fb[0] = 0xff00 << (SCREEN_WIDTH - 16);
+        let frames = [(fb, false), (fb, false), (fb, false)];
+
+        let bytes = encode_all(&frames, 2);
+
+        let mut decoder = Decoder::new();
+        let mut input = bytes.iter();
+        for (fb, hires) in frames.iter() {
+            let (decoded, decoded_hires) = decoder.next_frame(&mut input).unwrap();
+            assert_eq!(decoded, *fb);
+            assert_eq!(decoded_hires, *hires);
+        }
+        assert!(decoder.next_frame(&mut input).is_none());
+    }
+
+    #[test]
+    fn roundtrips_resolution_switch_and_changing_rows() {
+        let mut lo: FrameBuf = [0; SCREEN_HEIGHT as usize];
+        lo[5] = 0xaaaa << (SCREEN_WIDTH - 16);
+
+        let mut hi: FrameBuf = [0; SCREEN_HEIGHT as usize];
+        hi[5] = !0;
+        hi[40] = 0x1234_5678 << (SCREEN_WIDTH - 32);
+
+        let frames = [(lo, false), (hi, true)];
+        let bytes = encode_all(&frames, 100);
+
+        let mut decoder = Decoder::new();
+        let mut input = bytes.iter();
+        for (fb, hires) in frames.iter() {
+            let (decoded, decoded_hires) = decoder.next_frame(&mut input).unwrap();
+            assert_eq!(decoded, *fb);
+            assert_eq!(decoded_hires, *hires);
+        }
+    }
+}
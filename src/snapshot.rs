@@ -0,0 +1,54 @@
+//! Concatenates CPU state, RAM, and the framebuffer into a single buffer,
+//! so a frontend can snapshot and restore a running machine without having
+//! to know how the pieces are laid out.
+
+use prelude::*;
+use peripherals::Peripherals;
+use cpu::{self, CPU};
+
+const ROW_BYTES: usize = 16;
+const SCREEN_BYTES: usize = SCREEN_HEIGHT as usize * ROW_BYTES;
+
+pub const SNAPSHOT_SIZE: usize = cpu::STATE_SIZE + RAM_SIZE + SCREEN_BYTES;
+
+/// Write a full machine snapshot (CPU state, then RAM, then the
+/// `SCREEN_HEIGHT` `ScreenRow`s) into `out`, returning the number of bytes
+/// written. `out` must be at least `SNAPSHOT_SIZE` bytes long.
+pub fn save_snapshot<P: Peripherals>(cpu: &CPU, io: &P, out: &mut [Byte]) -> usize {
+    let out = &mut out[..SNAPSHOT_SIZE];
+
+    cpu.save_state(&mut out[..cpu::STATE_SIZE]);
+
+    let ram = &mut out[cpu::STATE_SIZE..cpu::STATE_SIZE + RAM_SIZE];
+    io.dump_ram(ram);
+
+    let fb = &mut out[cpu::STATE_SIZE + RAM_SIZE..];
+    for y in 0..SCREEN_HEIGHT {
+        let o = y as usize * ROW_BYTES;
+        fb[o..o + ROW_BYTES].copy_from_slice(&io.get_pixel_row(y).to_le_bytes());
+    }
+
+    SNAPSHOT_SIZE
+}
+
+/// Restore a snapshot previously written by `save_snapshot`.
+pub fn load_snapshot<P: Peripherals>(cpu: &mut CPU, io: &mut P, data: &[Byte]) -> Result<(), ()> {
+    if data.len() < SNAPSHOT_SIZE {
+        return Err(());
+    }
+
+    cpu.load_state(&data[..cpu::STATE_SIZE])?;
+
+    let ram = &data[cpu::STATE_SIZE..cpu::STATE_SIZE + RAM_SIZE];
+    io.load_ram(ram);
+
+    let fb = &data[cpu::STATE_SIZE + RAM_SIZE..SNAPSHOT_SIZE];
+    for y in 0..SCREEN_HEIGHT {
+        let o = y as usize * ROW_BYTES;
+        let mut bytes = [0; ROW_BYTES];
+        bytes.copy_from_slice(&fb[o..o + ROW_BYTES]);
+        io.set_pixel_row(y, ScreenRow::from_le_bytes(bytes));
+    }
+
+    Ok(())
+}